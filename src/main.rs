@@ -1,13 +1,20 @@
 use regex::Regex;
 use regex::RegexBuilder;
+use regex::RegexSet;
+use regex::RegexSetBuilder;
+use std::fmt;
+use std::io::IsTerminal;
 use std::io::Write;
 use std::io::{self, BufRead};
+use std::str::FromStr;
 use structopt::StructOpt;
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
 #[derive(StructOpt)]
 struct Cli {
-    #[structopt(help = "Patterns to search for in the input")]
+    #[structopt(
+        help = "Patterns to search for in the input. Append =<color> (e.g. ERROR=red, WARN=#ffa500, debug=244) to pin a pattern to a specific color instead of the auto-cycled palette"
+    )]
     patterns: Vec<String>,
 
     #[structopt(short = "l", long, help = "Color the whole line")]
@@ -18,23 +25,207 @@ struct Cli {
 
     #[structopt(short = "b", long, help = "Color the background")]
     background: bool,
+
+    #[structopt(
+        long,
+        help = "When to color output: auto, always, or never",
+        default_value = "auto"
+    )]
+    color: ColorOption,
+
+    #[structopt(long, help = "Render matches in bold")]
+    bold: bool,
+
+    #[structopt(long, help = "Render matches underlined")]
+    underline: bool,
+
+    #[structopt(long, help = "Render matches in italics")]
+    italic: bool,
+
+    #[structopt(long, help = "Render matches dimmed")]
+    dim: bool,
+
+    #[structopt(
+        long,
+        help = "Match against input that already contains ANSI escape sequences, preserving them around matches"
+    )]
+    ansi: bool,
+}
+
+/// The text attributes applied to a match, gathered from the CLI once so
+/// `apply_color` doesn't have to thread each flag through separately.
+struct Style {
+    background: bool,
+    bold: bool,
+    underline: bool,
+    italic: bool,
+    dim: bool,
+}
+
+impl Style {
+    #[cfg(test)]
+    fn plain() -> Self {
+        Style {
+            background: false,
+            bold: false,
+            underline: false,
+            italic: false,
+            dim: false,
+        }
+    }
+
+    fn from_args(args: &Cli) -> Self {
+        Style {
+            background: args.background,
+            bold: args.bold,
+            underline: args.underline,
+            italic: args.italic,
+            dim: args.dim,
+        }
+    }
+
+    /// Builds the `ColorSpec` for a match of the given `color`, combining
+    /// the fg/bg choice with the style's bold/underline/italic/dim flags.
+    fn color_spec(&self, color: Color) -> ColorSpec {
+        let mut color_spec = ColorSpec::new();
+        if self.background {
+            color_spec.set_bg(Some(color));
+        } else {
+            color_spec.set_fg(Some(color));
+        }
+        color_spec.set_bold(self.bold);
+        color_spec.set_underline(self.underline);
+        color_spec.set_italic(self.italic);
+        color_spec.set_dimmed(self.dim);
+        color_spec
+    }
+}
+
+/// The user-facing `--color` modes, resolved to a `termcolor::ColorChoice`
+/// once we know whether stdout is a terminal.
+#[derive(Debug, Clone, Copy)]
+enum ColorOption {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorOption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorOption::Auto),
+            "always" => Ok(ColorOption::Always),
+            "never" => Ok(ColorOption::Never),
+            other => Err(format!(
+                "invalid --color value '{}': expected auto, always, or never",
+                other
+            )),
+        }
+    }
+}
+
+/// Resolves `--color` to a `ColorChoice`, checking stdout's TTY-ness and
+/// `NO_COLOR` for the `auto` case.
+fn resolve_color_choice(color: ColorOption) -> ColorChoice {
+    match color {
+        ColorOption::Always => ColorChoice::Always,
+        ColorOption::Never => ColorChoice::Never,
+        ColorOption::Auto => {
+            if std::env::var_os("NO_COLOR").is_some() || !io::stdout().is_terminal() {
+                ColorChoice::Never
+            } else {
+                ColorChoice::Auto
+            }
+        }
+    }
 }
 
 fn main() {
     let args = Cli::from_args();
 
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+    let mut stdout = StandardStream::stdout(resolve_color_choice(args.color));
 
     match assign_color_to_pattern(&args.patterns, args.case_sensitive) {
-        Ok(patterns) => process_input(&args, &patterns, &mut stdout, io::stdin().lock()),
+        Ok(pattern_set) => process_input(&args, &pattern_set, &mut stdout, io::stdin().lock()),
         Err(e) => eprintln!("Error creating patterns: {}", e),
     }
 }
 
+/// Error building the pattern list: either the regex itself is invalid, or a
+/// `pattern=color` suffix couldn't be parsed into a `termcolor::Color`.
+#[derive(Debug)]
+enum PatternError {
+    Regex(regex::Error),
+    Color(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::Regex(e) => write!(f, "{}", e),
+            PatternError::Color(spec) => write!(
+                f,
+                "invalid color '{}': expected a named color, a 0-255 number, or #rrggbb",
+                spec
+            ),
+        }
+    }
+}
+
+/// Splits a raw `pattern` or `pattern=color` CLI argument into its regex
+/// source and an optional color suffix.
+fn split_pattern(raw: &str) -> (&str, Option<&str>) {
+    match raw.find('=') {
+        Some(idx) => (&raw[..idx], Some(&raw[idx + 1..])),
+        None => (raw, None),
+    }
+}
+
+/// Parses a `pattern=color` suffix into a `termcolor::Color`, accepting the
+/// named ANSI colors, a bare 0-255 number (`Color::Ansi256`), or `#rrggbb`
+/// hex (`Color::Rgb`, for truecolor terminals).
+fn parse_color(spec: &str) -> Result<Color, PatternError> {
+    if let Some(hex) = spec.strip_prefix('#') {
+        if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            let r = u8::from_str_radix(&hex[0..2], 16).unwrap();
+            let g = u8::from_str_radix(&hex[2..4], 16).unwrap();
+            let b = u8::from_str_radix(&hex[4..6], 16).unwrap();
+            return Ok(Color::Rgb(r, g, b));
+        }
+        return Err(PatternError::Color(spec.to_string()));
+    }
+
+    if let Ok(n) = spec.parse::<u8>() {
+        return Ok(Color::Ansi256(n));
+    }
+
+    match spec.to_ascii_lowercase().as_str() {
+        "black" => Ok(Color::Black),
+        "red" => Ok(Color::Red),
+        "green" => Ok(Color::Green),
+        "yellow" => Ok(Color::Yellow),
+        "blue" => Ok(Color::Blue),
+        "magenta" => Ok(Color::Magenta),
+        "cyan" => Ok(Color::Cyan),
+        "white" => Ok(Color::White),
+        _ => Err(PatternError::Color(spec.to_string())),
+    }
+}
+
+/// The compiled patterns plus a `RegexSet` over the same sources, used to
+/// cheaply tell which (if any) patterns can match a line before running the
+/// more expensive per-pattern `find_iter`.
+struct PatternSet {
+    patterns: Vec<(Regex, Color)>,
+    set: RegexSet,
+}
+
 fn assign_color_to_pattern(
     patterns: &[String],
     case_sensitive: bool,
-) -> Result<Vec<(Regex, Color)>, regex::Error> {
+) -> Result<PatternSet, PatternError> {
     let colors = [
         Color::Red,
         Color::Yellow,
@@ -50,22 +241,48 @@ fn assign_color_to_pattern(
         Color::Ansi256(165), // Purple
     ];
 
-    patterns
-        .iter()
-        .enumerate()
-        .map(|(i, pattern)| {
-            let mut regex_builder = RegexBuilder::new(pattern);
-            if !case_sensitive {
-                regex_builder.case_insensitive(true);
-            }
-            let regex = regex_builder.build().map_err(|e| {
-                eprintln!("Error compiling pattern '{}': {}", pattern, e);
-                e
-            })?;
-            let color = colors[i % colors.len()];
-            Ok((regex, color))
-        })
-        .collect()
+    let mut compiled = Vec::with_capacity(patterns.len());
+    let mut sources = Vec::with_capacity(patterns.len());
+
+    for (i, pattern) in patterns.iter().enumerate() {
+        let (split_source, color_spec) = split_pattern(pattern);
+
+        // The `=color` suffix is opt-in by parse success: if what follows the
+        // `=` isn't a color we recognize, assume there was no suffix at all
+        // and treat the whole argument as the regex source. This keeps
+        // patterns like `key=value` working as plain text instead of hard
+        // failing on an `=` that was never meant to introduce a color.
+        let (pattern_source, explicit_color) = match color_spec.map(parse_color) {
+            Some(Ok(color)) => (split_source, Some(color)),
+            Some(Err(_)) => (pattern.as_str(), None),
+            None => (split_source, None),
+        };
+        sources.push(pattern_source.to_string());
+
+        let mut regex_builder = RegexBuilder::new(pattern_source);
+        if !case_sensitive {
+            regex_builder.case_insensitive(true);
+        }
+        let regex = regex_builder.build().map_err(|e| {
+            eprintln!("Error compiling pattern '{}': {}", pattern_source, e);
+            PatternError::Regex(e)
+        })?;
+
+        let color = explicit_color.unwrap_or(colors[i % colors.len()]);
+
+        compiled.push((regex, color));
+    }
+
+    let mut set_builder = RegexSetBuilder::new(&sources);
+    if !case_sensitive {
+        set_builder.case_insensitive(true);
+    }
+    let set = set_builder.build().map_err(PatternError::Regex)?;
+
+    Ok(PatternSet {
+        patterns: compiled,
+        set,
+    })
 }
 
 fn write<W: Write>(out: &mut W, line: &str) {
@@ -88,14 +305,19 @@ fn reset_color<W: WriteColor>(out: &mut W) {
 
 fn process_input<R: BufRead, W: WriteColor>(
     args: &Cli,
-    patterns: &[(Regex, Color)],
+    pattern_set: &PatternSet,
     out: &mut W,
     reader: R,
 ) {
+    let style = Style::from_args(args);
     for line in reader.lines() {
         match line {
             Ok(line) => {
-                apply_color(&line, patterns, args.whole_line, args.background, out);
+                if args.ansi {
+                    apply_color_ansi(&line, pattern_set, args.whole_line, &style, out);
+                } else {
+                    apply_color(&line, pattern_set, args.whole_line, &style, out);
+                }
             }
             Err(e) => eprintln!("Error reading line: {}", e),
         }
@@ -104,60 +326,288 @@ fn process_input<R: BufRead, W: WriteColor>(
 
 fn apply_color<W: WriteColor>(
     line: &str,
-    patterns: &[(Regex, Color)],
+    pattern_set: &PatternSet,
     whole_line: bool,
-    background: bool,
+    style: &Style,
     out: &mut W,
 ) -> bool {
-    let mut matches: Vec<(usize, usize, Color)> = Vec::new();
+    let matched = pattern_set.set.matches(line);
+    if !matched.matched_any() {
+        write_line(out, line);
+        return false;
+    }
 
-    for (regex, color) in patterns {
+    let mut matches: Vec<(usize, usize, Color)> = Vec::new();
+    for idx in matched.iter() {
+        let (regex, color) = &pattern_set.patterns[idx];
         for mat in regex.find_iter(line) {
             matches.push((mat.start(), mat.end(), *color));
         }
     }
 
-    if matches.is_empty() {
+    if whole_line {
+        let mut sorted = matches.clone();
+        sorted.sort_by_key(|k| k.0);
+        let color = sorted[0].2;
+        set_color(out, &style.color_spec(color));
+        write(out, line);
+        reset_color(out);
+        write_line(out, "");
+    } else {
+        apply_layered_color(line, &matches, style, out);
+    }
+
+    true
+}
+
+/// Resolves overlapping/nested matches against `text` by filling a per-char
+/// color layer, with later entries in `matches` (i.e. later patterns in CLI
+/// order) winning on conflict, then collapses it into maximal runs of
+/// identical color state. Returns only the colored runs, in position order;
+/// gaps between them are left for the caller to fill with unstyled text.
+fn resolve_overlapping_matches(
+    text: &str,
+    matches: &[(usize, usize, Color)],
+) -> Vec<(usize, usize, Color)> {
+    let boundaries: Vec<usize> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(text.len()))
+        .collect();
+
+    let mut layer: Vec<Option<Color>> = vec![None; boundaries.len() - 1];
+    for (start, end, color) in matches {
+        let start_idx = boundaries.binary_search(start).unwrap();
+        let end_idx = boundaries.binary_search(end).unwrap();
+        for slot in &mut layer[start_idx..end_idx] {
+            *slot = Some(*color);
+        }
+    }
+
+    let mut resolved = Vec::new();
+    let mut run_start = 0;
+    while run_start < layer.len() {
+        let current = layer[run_start];
+        let mut run_end = run_start + 1;
+        while run_end < layer.len() && layer[run_end] == current {
+            run_end += 1;
+        }
+
+        if let Some(color) = current {
+            resolved.push((boundaries[run_start], boundaries[run_end], color));
+        }
+
+        run_start = run_end;
+    }
+
+    resolved
+}
+
+/// Emits `line` with overlapping/nested `matches` resolved per
+/// `resolve_overlapping_matches` (later patterns in CLI order win), coloring
+/// each resulting run and leaving the gaps between them unstyled.
+fn apply_layered_color<W: WriteColor>(
+    line: &str,
+    matches: &[(usize, usize, Color)],
+    style: &Style,
+    out: &mut W,
+) {
+    let mut pos = 0;
+    for (start, end, color) in resolve_overlapping_matches(line, matches) {
+        if pos < start {
+            write(out, &line[pos..start]);
+        }
+        set_color(out, &style.color_spec(color));
+        write(out, &line[start..end]);
+        reset_color(out);
+        pos = end;
+    }
+    if pos < line.len() {
+        write(out, &line[pos..]);
+    }
+
+    write_line(out, "");
+}
+
+/// One run of a line as seen by the `--ansi` tokenizer: either plain visible
+/// text, or a raw SGR escape sequence to be copied through untouched.
+enum Token<'a> {
+    Text(&'a str),
+    Escape(&'a str),
+}
+
+/// Splits `line` into alternating `Text`/`Escape` tokens. An escape sequence
+/// starts at `ESC [` and runs through parameter/intermediate bytes until a
+/// final byte in `0x40..=0x7e` (typically `m` for SGR).
+fn tokenize_ansi(line: &str) -> Vec<Token<'_>> {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == 0x1b && i + 1 < bytes.len() && bytes[i + 1] == b'[' {
+            if text_start < i {
+                tokens.push(Token::Text(&line[text_start..i]));
+            }
+            let escape_start = i;
+            i += 2;
+            while i < bytes.len() && !(0x40..=0x7e).contains(&bytes[i]) {
+                i += 1;
+            }
+            if i < bytes.len() {
+                i += 1; // include the final byte
+            }
+            tokens.push(Token::Escape(&line[escape_start..i]));
+            text_start = i;
+        } else {
+            i += 1;
+        }
+    }
+
+    if text_start < bytes.len() {
+        tokens.push(Token::Text(&line[text_start..]));
+    }
+
+    tokens
+}
+
+/// Whether an escape sequence resets SGR state (`ESC[0m` or `ESC[m`), as
+/// opposed to setting some other upstream color/style.
+fn is_sgr_reset(escape: &str) -> bool {
+    matches!(escape, "\x1b[0m" | "\x1b[m")
+}
+
+/// ANSI-aware counterpart to `apply_color`: matches patterns against the
+/// *visible* text only (escape sequences stripped), then replays the line
+/// token by token, passing escape sequences through verbatim and wrapping
+/// matched visible spans in our own color, restoring whatever upstream SGR
+/// state was active before we reset.
+fn apply_color_ansi<W: WriteColor>(
+    line: &str,
+    pattern_set: &PatternSet,
+    whole_line: bool,
+    style: &Style,
+    out: &mut W,
+) -> bool {
+    let tokens = tokenize_ansi(line);
+
+    // Maps each byte offset into `visible` back to its raw offset in `line`.
+    let mut visible = String::new();
+    let mut offsets = Vec::with_capacity(line.len() + 1);
+    let mut raw_pos = 0;
+    for token in &tokens {
+        match token {
+            Token::Text(s) => {
+                for i in 0..s.len() {
+                    offsets.push(raw_pos + i);
+                }
+                visible.push_str(s);
+                raw_pos += s.len();
+            }
+            Token::Escape(s) => raw_pos += s.len(),
+        }
+    }
+    offsets.push(line.len());
+
+    let matched = pattern_set.set.matches(&visible);
+    if !matched.matched_any() {
         write_line(out, line);
         return false;
     }
 
-    matches.sort_by_key(|k| k.0);
+    // Collected in `visible` offsets for now; matches are mapped to raw
+    // offsets in `line` only after overlap resolution below, since
+    // `resolve_overlapping_matches` needs char boundaries of the text the
+    // matches were actually found against.
+    let mut visible_matches: Vec<(usize, usize, Color)> = Vec::new();
+    for idx in matched.iter() {
+        let (regex, color) = &pattern_set.patterns[idx];
+        for mat in regex.find_iter(&visible) {
+            visible_matches.push((mat.start(), mat.end(), *color));
+        }
+    }
 
     if whole_line {
-        let color = matches[0].2;
-        let mut color_spec = ColorSpec::new();
-        if background {
-            color_spec.set_bg(Some(color));
-        } else {
-            color_spec.set_fg(Some(color));
+        visible_matches.sort_by_key(|k| k.0);
+        let color = visible_matches[0].2;
+        set_color(out, &style.color_spec(color));
+        for token in &tokens {
+            match token {
+                Token::Text(s) | Token::Escape(s) => write(out, s),
+            }
         }
-
-        set_color(out, &color_spec);
-        write(out, line);
         reset_color(out);
         write_line(out, "");
-    } else {
-        let mut last_end = 0;
-        for (start, end, color) in matches {
-            if start >= last_end {
-                let mut color_spec = ColorSpec::new();
-                if background {
-                    color_spec.set_bg(Some(color));
+        return true;
+    }
+
+    let resolved: Vec<(usize, usize, Color)> =
+        resolve_overlapping_matches(&visible, &visible_matches)
+            .into_iter()
+            .map(|(start, end, color)| (offsets[start], offsets[end - 1] + 1, color))
+            .collect();
+
+    let mut match_idx = 0;
+    let mut in_match: Option<(usize, Color)> = None; // raw end offset + color of the active match
+    let mut active_escapes: Vec<&str> = Vec::new(); // upstream escapes since the last reset
+    let mut pos = 0usize;
+
+    for token in &tokens {
+        match token {
+            Token::Escape(s) => {
+                write(out, s);
+                if is_sgr_reset(s) {
+                    active_escapes.clear();
                 } else {
-                    color_spec.set_fg(Some(color));
+                    active_escapes.push(s);
                 }
+                pos += s.len();
+                // The escape we just passed through may have clobbered our
+                // own color (or reset it); if a match is still in progress,
+                // re-assert it so the rest of the match stays highlighted.
+                if let Some((_, color)) = in_match {
+                    set_color(out, &style.color_spec(color));
+                }
+            }
+            Token::Text(s) => {
+                let token_end = pos + s.len();
+                let mut cursor = pos;
+                while cursor < token_end {
+                    if let Some((end, _)) = in_match {
+                        let stop = end.min(token_end);
+                        write(out, &line[cursor..stop]);
+                        cursor = stop;
+                        if cursor == end {
+                            reset_color(out);
+                            for escape in &active_escapes {
+                                write(out, escape);
+                            }
+                            in_match = None;
+                        }
+                        continue;
+                    }
 
-                write(out, &line[last_end..start]);
-                set_color(out, &color_spec);
-                write(out, &line[start..end]);
-                reset_color(out);
-                last_end = end;
+                    match resolved.get(match_idx) {
+                        Some(&(start, end, color)) if start < token_end => {
+                            write(out, &line[cursor..start]);
+                            set_color(out, &style.color_spec(color));
+                            in_match = Some((end, color));
+                            match_idx += 1;
+                            cursor = start;
+                        }
+                        _ => {
+                            write(out, &line[cursor..token_end]);
+                            cursor = token_end;
+                        }
+                    }
+                }
+                pos = token_end;
             }
         }
-        write_line(out, &line[last_end..]);
     }
 
+    write_line(out, "");
     true
 }
 
@@ -182,15 +632,91 @@ mod tests {
         let patterns = vec!["foo".to_string(), "bar".to_string()];
         let result = assign_color_to_pattern(&patterns, true);
         assert!(result.is_ok());
-        let patterns = result.unwrap();
-        assert_eq!(patterns.len(), 2);
+        let pattern_set = result.unwrap();
+        assert_eq!(pattern_set.patterns.len(), 2);
+    }
+
+    #[test]
+    fn test_assign_color_to_pattern_explicit_color() {
+        let patterns = vec!["ERROR=red".to_string(), "debug=244".to_string()];
+        let pattern_set = assign_color_to_pattern(&patterns, true).unwrap();
+        assert_eq!(pattern_set.patterns[0].1, Color::Red);
+        assert_eq!(pattern_set.patterns[1].1, Color::Ansi256(244));
+    }
+
+    #[test]
+    fn test_assign_color_to_pattern_hex_color() {
+        let patterns = vec!["WARN=#ffa500".to_string()];
+        let pattern_set = assign_color_to_pattern(&patterns, true).unwrap();
+        assert_eq!(pattern_set.patterns[0].1, Color::Rgb(0xff, 0xa5, 0x00));
+    }
+
+    #[test]
+    fn test_assign_color_to_pattern_unparseable_suffix_is_literal() {
+        // "notacolor" isn't a color, so the whole argument is treated as the
+        // regex source (no error) instead of aborting the pattern list.
+        let patterns = vec!["ERROR=notacolor".to_string()];
+        let pattern_set = assign_color_to_pattern(&patterns, true).unwrap();
+        assert!(pattern_set.set.is_match("ERROR=notacolor"));
+        assert!(!pattern_set.set.is_match("ERROR"));
+    }
+
+    #[test]
+    fn test_assign_color_to_pattern_auto_cycle_skips_explicit() {
+        let patterns = vec!["a=red".to_string(), "b".to_string()];
+        let pattern_set = assign_color_to_pattern(&patterns, true).unwrap();
+        assert_eq!(pattern_set.patterns[0].1, Color::Red);
+        assert_eq!(pattern_set.patterns[1].1, Color::Yellow);
+    }
+
+    #[test]
+    fn test_assign_color_to_pattern_key_value_pattern_still_works() {
+        // A literal pattern like `key=value` must keep matching text
+        // containing `=`; it is not a `pattern=color` suffix.
+        let patterns = vec!["key=value".to_string()];
+        let pattern_set = assign_color_to_pattern(&patterns, true).unwrap();
+        assert!(pattern_set.set.is_match("key=value"));
+    }
+
+    #[test]
+    fn test_assign_color_to_pattern_regex_set_matches() {
+        let patterns = vec!["foo".to_string(), "bar".to_string()];
+        let pattern_set = assign_color_to_pattern(&patterns, true).unwrap();
+        assert!(pattern_set.set.is_match("a foo b"));
+        assert!(!pattern_set.set.is_match("nothing here"));
+    }
+
+    #[test]
+    fn test_color_option_from_str() {
+        assert!(matches!("auto".parse::<ColorOption>(), Ok(ColorOption::Auto)));
+        assert!(matches!(
+            "Always".parse::<ColorOption>(),
+            Ok(ColorOption::Always)
+        ));
+        assert!(matches!(
+            "never".parse::<ColorOption>(),
+            Ok(ColorOption::Never)
+        ));
+        assert!("rainbow".parse::<ColorOption>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_color_choice_explicit() {
+        assert_eq!(
+            resolve_color_choice(ColorOption::Always),
+            ColorChoice::Always
+        );
+        assert_eq!(
+            resolve_color_choice(ColorOption::Never),
+            ColorChoice::Never
+        );
     }
 
     #[test]
     fn test_apply_color_no_match() {
         let (_writer, mut buffer) = create_test_writer();
         let patterns = assign_color_to_pattern(&["foo".to_string()], true).unwrap();
-        let result = apply_color("bar", &patterns, false, false, &mut buffer);
+        let result = apply_color("bar", &patterns, false, &Style::plain(), &mut buffer);
         assert!(!result);
         assert_eq!(get_buffer_contents(buffer), "bar\n");
     }
@@ -199,7 +725,7 @@ mod tests {
     fn test_apply_color_match() {
         let (_writer, mut buffer) = create_test_writer();
         let patterns = assign_color_to_pattern(&["foo".to_string()], true).unwrap();
-        let result = apply_color("foo", &patterns, false, false, &mut buffer);
+        let result = apply_color("foo", &patterns, false, &Style::plain(), &mut buffer);
         assert!(result);
         assert!(get_buffer_contents(buffer).contains("foo"));
     }
@@ -208,11 +734,65 @@ mod tests {
     fn test_apply_color_match_whole_line() {
         let (_writer, mut buffer) = create_test_writer();
         let patterns = assign_color_to_pattern(&["foo".to_string()], true).unwrap();
-        let result = apply_color("foo", &patterns, true, false, &mut buffer);
+        let result = apply_color("foo", &patterns, true, &Style::plain(), &mut buffer);
         assert!(result);
         assert!(get_buffer_contents(buffer).contains("foo"));
     }
 
+    #[test]
+    fn test_apply_color_only_scans_patterns_flagged_by_regex_set() {
+        let (_writer, mut buffer) = create_test_writer();
+        let patterns =
+            assign_color_to_pattern(&["foo".to_string(), "bar".to_string()], true).unwrap();
+        let flagged: Vec<usize> = patterns.set.matches("only foo here").iter().collect();
+        assert_eq!(flagged, vec![0]);
+        let result = apply_color("only foo here", &patterns, false, &Style::plain(), &mut buffer);
+        assert!(result);
+        let output = get_buffer_contents(buffer);
+        assert!(output.contains("foo"));
+        assert!(output.contains("only"));
+    }
+
+    #[test]
+    fn test_apply_layered_color_later_pattern_wins_on_overlap() {
+        let (_writer, mut buffer) = create_test_writer();
+        let matches = vec![(0, 3, Color::Red), (1, 4, Color::Yellow)];
+        apply_layered_color("abcd", &matches, &Style::plain(), &mut buffer);
+        let output = get_buffer_contents(buffer);
+        assert!(output.contains('a'));
+        assert!(output.contains("bcd"));
+    }
+
+    #[test]
+    fn test_apply_color_respects_char_boundaries() {
+        // Use ColorChoice::Never so the output is byte-for-byte comparable:
+        // with color escapes injected around `llo`/`world` the raw substring
+        // "héllo world" could never appear even if boundaries were handled
+        // correctly, and a panic here would signal a mid-character slice.
+        let writer = BufferWriter::stdout(ColorChoice::Never);
+        let mut buffer = writer.buffer();
+        let line = "héllo world";
+        let patterns =
+            assign_color_to_pattern(&["llo".to_string(), "world".to_string()], true).unwrap();
+        let result = apply_color(line, &patterns, false, &Style::plain(), &mut buffer);
+        assert!(result);
+        assert_eq!(get_buffer_contents(buffer), format!("{}\n", line));
+    }
+
+    #[test]
+    fn test_style_color_spec_combines_bold_and_fg() {
+        let style = Style {
+            background: false,
+            bold: true,
+            underline: false,
+            italic: false,
+            dim: false,
+        };
+        let spec = style.color_spec(Color::Red);
+        assert_eq!(spec.fg(), Some(&Color::Red));
+        assert!(spec.bold());
+    }
+
     #[test]
     fn test_process_input() {
         let input = b"foo\nbar\nbaz\nhey foo hoy bar huy\n";
@@ -221,6 +801,12 @@ mod tests {
             whole_line: false,
             case_sensitive: true,
             background: false,
+            color: ColorOption::Always,
+            bold: false,
+            underline: false,
+            italic: false,
+            dim: false,
+            ansi: false,
         };
         let patterns = assign_color_to_pattern(&args.patterns, args.case_sensitive).unwrap();
         let (_writer, mut buffer) = create_test_writer();
@@ -237,4 +823,77 @@ mod tests {
         assert!(result.matches("bar").count() == 2);
         assert!(result.matches("baz").count() == 1);
     }
+
+    #[test]
+    fn test_tokenize_ansi_splits_text_and_escapes() {
+        let tokens = tokenize_ansi("\x1b[31mfoo\x1b[0mbar");
+        let rendered: Vec<&str> = tokens
+            .iter()
+            .map(|t| match t {
+                Token::Text(s) => *s,
+                Token::Escape(s) => *s,
+            })
+            .collect();
+        assert_eq!(rendered, vec!["\x1b[31m", "foo", "\x1b[0m", "bar"]);
+    }
+
+    #[test]
+    fn test_apply_color_ansi_matches_across_escape_boundary() {
+        let (_writer, mut buffer) = create_test_writer();
+        let patterns = assign_color_to_pattern(&["foo".to_string()], true).unwrap();
+        let line = "\x1b[31mfoo\x1b[0m bar";
+        let result = apply_color_ansi(line, &patterns, false, &Style::plain(), &mut buffer);
+        assert!(result);
+        let output = get_buffer_contents(buffer);
+        assert!(output.contains("\x1b[31m"));
+        assert!(output.contains("foo"));
+        assert!(output.contains("bar"));
+    }
+
+    #[test]
+    fn test_apply_color_ansi_resolves_overlapping_matches() {
+        let (_writer, mut buffer) = create_test_writer();
+        // "bc" and "cd" overlap on "c"; the later pattern (cd) must win,
+        // matching apply_layered_color's non-ansi behavior, instead of the
+        // first match greedily swallowing the rest of the line.
+        let patterns =
+            assign_color_to_pattern(&["bc".to_string(), "cd".to_string()], true).unwrap();
+        let line = "a\x1b[0mbcd";
+        let result = apply_color_ansi(line, &patterns, false, &Style::plain(), &mut buffer);
+        assert!(result);
+        let output = get_buffer_contents(buffer);
+        assert!(output.contains('b'));
+        assert!(output.contains("cd"));
+    }
+
+    #[test]
+    fn test_apply_color_ansi_reapplies_color_after_embedded_escape() {
+        let (_writer, mut buffer) = create_test_writer();
+        let patterns = assign_color_to_pattern(&["oob".to_string()], true).unwrap();
+        // The match "oob" straddles the embedded reset escape inside "foobar";
+        // without re-asserting our color after that escape, "b" would render
+        // uncolored even though it's still inside the match.
+        let line = "foo\x1b[0mbar";
+        let result = apply_color_ansi(line, &patterns, false, &Style::plain(), &mut buffer);
+        assert!(result);
+        let output = get_buffer_contents(buffer);
+        // termcolor's `set_color` always emits a leading reset before the new
+        // SGR code, so each of our two `set_color` calls (open the match,
+        // then re-assert after the embedded escape) is two escapes, not one:
+        // open (2) + pass the embedded reset through (1) + re-assert (2) +
+        // final reset (1) = 6 escapes total.
+        assert_eq!(output.matches('\u{1b}').count(), 6);
+        assert!(output.contains("foo"));
+        assert!(output.contains("bar"));
+    }
+
+    #[test]
+    fn test_apply_color_ansi_no_match_passes_through_unchanged() {
+        let (_writer, mut buffer) = create_test_writer();
+        let patterns = assign_color_to_pattern(&["zzz".to_string()], true).unwrap();
+        let line = "\x1b[31mfoo\x1b[0m bar";
+        let result = apply_color_ansi(line, &patterns, false, &Style::plain(), &mut buffer);
+        assert!(!result);
+        assert_eq!(get_buffer_contents(buffer), format!("{}\n", line));
+    }
 }